@@ -0,0 +1,242 @@
+use crate::config::Config;
+use crate::fmt::color_repo;
+use crate::upgrade::Upgrades;
+use crate::util::{input, NumberMenu};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use alpm_utils::DbListExt;
+use anyhow::{Context, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacnewKind {
+    New,
+    Save,
+}
+
+impl PacnewKind {
+    fn ext(self) -> &'static str {
+        match self {
+            PacnewKind::New => "pacnew",
+            PacnewKind::Save => "pacsave",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PacnewFile {
+    pub pkg: String,
+    pub kind: PacnewKind,
+    pub path: PathBuf,
+    pub orig: PathBuf,
+}
+
+// Appends `.pacnew`/`.pacsave` to `orig`'s existing extension (or as a bare
+// extension for extensionless files/dotfiles), e.g. `/etc/foo.conf` ->
+// `/etc/foo.conf.pacnew`, `/etc/foo` -> `/etc/foo.pacnew`.
+fn pacnew_path(orig: &Path, kind: PacnewKind) -> PathBuf {
+    orig.with_extension(
+        orig.extension()
+            .map(|ext| format!("{}.{}", ext.to_string_lossy(), kind.ext()))
+            .unwrap_or_else(|| kind.ext().to_string()),
+    )
+}
+
+// Intersects the given installed packages against alpm's file lists to find
+// the .pacnew/.pacsave files currently beside their files, instead of walking
+// the whole filesystem.
+fn find_pacnew_files(config: &Config, pkgs: &[String]) -> Result<Vec<PacnewFile>> {
+    let db = config.alpm.localdb();
+    let root = Path::new(config.alpm.root());
+    let mut found = Vec::new();
+
+    for pkg in pkgs {
+        let pkg = match db.pkg(pkg.as_str()) {
+            Ok(pkg) => pkg,
+            Err(_) => continue,
+        };
+
+        for file in pkg.files().files() {
+            let orig = root.join(file.name());
+
+            for kind in [PacnewKind::New, PacnewKind::Save] {
+                let path = pacnew_path(&orig, kind);
+
+                if path.exists() {
+                    found.push(PacnewFile {
+                        pkg: pkg.name().to_string(),
+                        kind,
+                        path,
+                        orig: orig.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+fn print_diff(config: &Config, file: &PacnewFile) -> Result<()> {
+    let old = fs::read_to_string(&file.orig).unwrap_or_default();
+    let new = fs::read_to_string(&file.path).unwrap_or_default();
+    let c = config.color;
+
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+
+    for i in 0..old_lines.len().max(new_lines.len()) {
+        match (old_lines.get(i), new_lines.get(i)) {
+            (Some(o), Some(n)) if o == n => println!(" {}", o),
+            (Some(o), Some(n)) => {
+                println!("-{}", c.old_version.paint(*o));
+                println!("+{}", c.new_version.paint(*n));
+            }
+            (Some(o), None) => println!("-{}", c.old_version.paint(*o)),
+            (None, Some(n)) => println!("+{}", c.new_version.paint(*n)),
+            (None, None) => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn resolve(config: &Config, file: &PacnewFile) -> Result<()> {
+    let diffprog = env::var("DIFFPROG").unwrap_or_default();
+    let interactive = !diffprog.is_empty();
+
+    if interactive {
+        Command::new(&diffprog)
+            .arg(&file.orig)
+            .arg(&file.path)
+            .status()
+            .with_context(|| format!("failed to run $DIFFPROG ({})", diffprog))?;
+    } else {
+        print_diff(config, file)?;
+    }
+
+    // Only offer "merge" once an interactive $DIFFPROG actually ran: the
+    // built-in print_diff above is read-only, so without one there's nothing
+    // the user could have reconciled in `orig` for "merge" to assume happened.
+    let prompt = if interactive {
+        "(M)erge, (O)verwrite, (S)kip?"
+    } else {
+        "(O)verwrite, (S)kip?"
+    };
+    let action = input(config, prompt);
+
+    match action.trim().to_lowercase().as_str() {
+        // The diff tool already ran against `orig` and `file.path` side by
+        // side; "merge" assumes the user reconciled `orig` by hand there
+        // (e.g. inside an interactive $DIFFPROG like vimdiff), so just drop
+        // the now-handled pacnew/pacsave and leave `orig` as-is.
+        "m" | "merge" if interactive => {
+            fs::remove_file(&file.path)
+                .with_context(|| format!("failed to remove {}", file.path.display()))?;
+        }
+        "m" | "merge" => {
+            let c = config.color;
+            println!(
+                "{} no $DIFFPROG is set, so there's nothing to merge; skipping",
+                c.warning.paint("warning:")
+            );
+        }
+        // "overwrite" is the only path that force-replaces `orig`, discarding
+        // whatever is currently there in favor of the pacnew/pacsave content.
+        "o" | "overwrite" => {
+            fs::rename(&file.path, &file.orig)
+                .with_context(|| format!("failed to overwrite {}", file.orig.display()))?;
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+pub async fn run(config: &Config, upgrades: &Upgrades) -> Result<()> {
+    if !config.pacdiff || config.no_confirm {
+        return Ok(());
+    }
+
+    let pkgs = upgrades
+        .repo_keep
+        .iter()
+        .chain(upgrades.aur_keep.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let files = find_pacnew_files(config, &pkgs)?;
+
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    let c = config.color;
+    println!(
+        "{} {}",
+        c.action.paint("::"),
+        c.bold.paint("Found configuration file changes")
+    );
+
+    let n_max = files.len().to_string().len();
+
+    for (n, file) in files.iter().enumerate() {
+        println!(
+            "{} {}/{} {}",
+            c.number_menu.paint(format!("{:>pad$}", n + 1, pad = n_max)),
+            color_repo(config.color.enabled, &file.pkg),
+            file.kind.ext(),
+            file.path.display(),
+        );
+    }
+
+    let input = input(config, "Files to view/merge (eg: 1 2 3, 1-3):");
+    let number_menu = NumberMenu::new(input.trim());
+
+    for (n, file) in files.iter().enumerate() {
+        if number_menu.contains(n + 1, "pacdiff") {
+            resolve(config, file)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pacnew_path_appends_to_existing_extension() {
+        let orig = Path::new("/etc/pacman.conf");
+        assert_eq!(
+            pacnew_path(orig, PacnewKind::New),
+            Path::new("/etc/pacman.conf.pacnew")
+        );
+        assert_eq!(
+            pacnew_path(orig, PacnewKind::Save),
+            Path::new("/etc/pacman.conf.pacsave")
+        );
+    }
+
+    #[test]
+    fn pacnew_path_handles_extensionless_files() {
+        let orig = Path::new("/etc/hosts");
+        assert_eq!(
+            pacnew_path(orig, PacnewKind::New),
+            Path::new("/etc/hosts.pacnew")
+        );
+    }
+
+    #[test]
+    fn pacnew_path_handles_dotfiles() {
+        let orig = Path::new("/etc/.dotfile");
+        assert_eq!(
+            pacnew_path(orig, PacnewKind::New),
+            Path::new("/etc/.dotfile.pacnew")
+        );
+    }
+}
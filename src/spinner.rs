@@ -0,0 +1,165 @@
+use crate::config::Config;
+
+use std::io::{IsTerminal, Write};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+const FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+// Tracks how many terminal rows are currently reserved for concurrent
+// spinners (e.g. the AUR and devel lookups in `aur_upgrades` run side by
+// side), so each one can redraw its own line in place instead of clobbering
+// whichever spinner most recently wrote to the cursor's row.
+struct Rows {
+    next: usize,
+    bottom: usize,
+    active: usize,
+}
+
+static ROWS: OnceLock<Mutex<Rows>> = OnceLock::new();
+
+fn rows() -> &'static Mutex<Rows> {
+    ROWS.get_or_init(|| {
+        Mutex::new(Rows {
+            next: 0,
+            bottom: 0,
+            active: 0,
+        })
+    })
+}
+
+// Claims the next free row, printing a blank line to make room for it if the
+// terminal hasn't grown that far yet.
+fn reserve_row() -> usize {
+    let mut rows = rows().lock().unwrap();
+    let row = rows.next;
+    rows.next += 1;
+    rows.active += 1;
+
+    if row >= rows.bottom {
+        println!();
+        rows.bottom += 1;
+    }
+
+    row
+}
+
+// Releases a row once its spinner is done. Once every row is released the
+// counters reset, so a later, unrelated batch of spinners starts from row 0
+// again instead of drifting further down the terminal with each call.
+fn release_row() {
+    let mut rows = rows().lock().unwrap();
+    rows.active -= 1;
+
+    if rows.active == 0 {
+        rows.next = 0;
+        rows.bottom = 0;
+    }
+}
+
+// Rewrites `row`'s line in place: move the cursor up from the shared bottom
+// row, overwrite the line, then move back down, so two spinners animating
+// concurrently each stay on their own row instead of fighting over one.
+fn draw_row(row: usize, text: &str) {
+    let up = rows().lock().unwrap().bottom - row;
+
+    if up == 0 {
+        print!("\r\x1b[K{}", text);
+    } else {
+        print!("\x1b[{up}A\r\x1b[K{text}\x1b[{up}B");
+    }
+
+    let _ = std::io::stdout().flush();
+}
+
+// A small spinner driven by a background tokio task, for giving feedback during
+// the concurrent AUR/devel upgrade lookups in `aur_upgrades`. Falls back to the
+// old static `:: message` line when stdout isn't a tty or color is disabled.
+pub struct Spinner {
+    message: String,
+    // The row this spinner owns, and the background task animating it.
+    animated: Option<(usize, oneshot::Sender<()>, JoinHandle<()>)>,
+}
+
+impl Spinner {
+    pub fn new(config: &Config, message: &str) -> Spinner {
+        let c = config.color;
+
+        if !config.color.enabled || !std::io::stdout().is_terminal() {
+            println!("{} {}", c.action.paint("::"), c.bold.paint(message));
+            return Spinner {
+                message: message.to_string(),
+                animated: None,
+            };
+        }
+
+        let row = reserve_row();
+        let (stop_tx, mut stop_rx) = oneshot::channel();
+        let msg = message.to_string();
+        let action = c.action;
+        let bold = c.bold;
+
+        let task = tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(80));
+            let mut frame = 0usize;
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        draw_row(
+                            row,
+                            &format!("{} {}", action.paint(FRAMES[frame % FRAMES.len()]), bold.paint(&msg)),
+                        );
+                        frame += 1;
+                    }
+                    _ = &mut stop_rx => break,
+                }
+            }
+        });
+
+        Spinner {
+            message: message.to_string(),
+            animated: Some((row, stop_tx, task)),
+        }
+    }
+
+    pub async fn finish(mut self, config: &Config, result: &str) {
+        let c = config.color;
+
+        if let Some((row, stop, task)) = self.animated.take() {
+            let _ = stop.send(());
+            let _ = task.await;
+            draw_row(
+                row,
+                &format!(
+                    "{} {} {}",
+                    c.action.paint("::"),
+                    c.bold.paint(&self.message),
+                    c.bold.paint(result)
+                ),
+            );
+            release_row();
+        }
+    }
+}
+
+impl Drop for Spinner {
+    // If the spinner is dropped without finish() running (e.g. the awaited
+    // call errored and `?` returned early), abort the background task so it
+    // stops animating at its next await point (the stop sender is dropped here
+    // too, which on its own also ends the task's loop). Note that tokio's
+    // abort() is cooperative: it cancels the task the next time it's polled,
+    // so there's a narrow (in practice sub-tick) window where the task could
+    // still be mid `print!`/flush when this returns. We accept that residual
+    // race rather than block synchronously here, since Drop has no `.await`.
+    fn drop(&mut self) {
+        if let Some((_, _, task)) = self.animated.take() {
+            task.abort();
+            release_row();
+        }
+    }
+}
@@ -1,12 +1,17 @@
 use crate::config::Config;
 use crate::devel::{filter_devel_updates, possible_devel_updates};
 use crate::fmt::color_repo;
+use crate::pacdiff;
+use crate::spinner::Spinner;
 use crate::util::{input, NumberMenu};
 
+use std::collections::HashMap;
+
 use alpm_utils::DbListExt;
 use anyhow::Result;
 use aur_depends::{AurUpdates, Resolver};
 use futures::try_join;
+use serde::Serialize;
 
 #[derive(Default, Debug)]
 pub struct Upgrades {
@@ -41,12 +46,10 @@ pub fn repo_upgrades(config: &Config) -> Result<Vec<alpm::Package>> {
     Ok(pkgs)
 }
 
-fn get_version_diff(config: &Config, old: &str, new: &str) -> (String, String) {
+fn version_diff_common_len(old: &str, new: &str) -> usize {
     let mut old_iter = old.chars();
     let mut new_iter = new.chars();
     let mut old_split = old_iter.clone();
-    let old_col = config.color.old_version;
-    let new_col = config.color.new_version;
 
     while let Some(old_c) = old_iter.next() {
         let new_c = match new_iter.next() {
@@ -63,7 +66,13 @@ fn get_version_diff(config: &Config, old: &str, new: &str) -> (String, String) {
         }
     }
 
-    let common = old.len() - old_split.as_str().len();
+    old.len() - old_split.as_str().len()
+}
+
+pub(crate) fn get_version_diff(config: &Config, old: &str, new: &str) -> (String, String) {
+    let common = version_diff_common_len(old, new);
+    let old_col = config.color.old_version;
+    let new_col = config.color.new_version;
 
     (
         format!("{}{}", &old[..common], old_col.paint(&old[common..])),
@@ -71,6 +80,47 @@ fn get_version_diff(config: &Config, old: &str, new: &str) -> (String, String) {
     )
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ChangeKind {
+    Rebuild,
+    Epoch,
+    Update,
+}
+
+impl ChangeKind {
+    fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Rebuild => "rebuild",
+            ChangeKind::Epoch => "epoch change",
+            ChangeKind::Update => "update",
+        }
+    }
+}
+
+fn split_epoch(ver: &str) -> (&str, &str) {
+    ver.split_once(':').unwrap_or(("0", ver))
+}
+
+// Classifies a pending change by comparing the `epoch:pkgver-pkgrel` components of
+// the old and new version, cargo-lockfile-report style.
+fn classify_change(old: &str, new: &str) -> ChangeKind {
+    let (old_epoch, old_rest) = split_epoch(old);
+    let (new_epoch, new_rest) = split_epoch(new);
+
+    if old_epoch != new_epoch {
+        return ChangeKind::Epoch;
+    }
+
+    let old_pkgver = old_rest.rsplit_once('-').map_or(old_rest, |(v, _)| v);
+    let new_pkgver = new_rest.rsplit_once('-').map_or(new_rest, |(v, _)| v);
+
+    if old_pkgver == new_pkgver {
+        ChangeKind::Rebuild
+    } else {
+        ChangeKind::Update
+    }
+}
+
 fn print_upgrade(
     config: &Config,
     n: usize,
@@ -81,6 +131,7 @@ fn print_upgrade(
     old: &str,
     old_max: usize,
     new: &str,
+    kind: ChangeKind,
 ) {
     let c = config.color;
     let n = format!("{:>pad$}", n, pad = n_max);
@@ -91,14 +142,20 @@ fn print_upgrade(
         "",
         pad = db_pkg_max - (db.len() + pkg.len()) + 1
     );
+    let tag = match kind {
+        ChangeKind::Rebuild => format!(" {}", c.warning.paint("(rebuild)")),
+        ChangeKind::Epoch => format!(" {}", c.action.paint("(epoch change)")),
+        ChangeKind::Update => String::new(),
+    };
     let old = format!("{:<pad$}", old, pad = old_max);
     let (old, new) = get_version_diff(config, &old, new);
     println!(
-        "{} {} {} -> {}",
+        "{} {} {} -> {}{}",
         c.number_menu.paint(n),
         c.bold.paint(db_pkg),
         old,
-        new
+        new,
+        tag
     );
 }
 
@@ -108,15 +165,16 @@ async fn get_aur_only_upgrades<'a, 'b>(
     print: bool,
 ) -> Result<AurUpdates<'a>> {
     if config.mode != "repo" {
-        if print {
-            let c = config.color;
-            println!(
-                "{} {}",
-                c.action.paint("::"),
-                c.bold.paint("Looking for AUR upgrades")
-            );
+        let spinner = print.then(|| Spinner::new(config, "Looking for AUR upgrades"));
+        let updates = resolver.aur_updates().await?;
+
+        if let Some(spinner) = spinner {
+            spinner
+                .finish(config, &format!("{} AUR upgrades", updates.updates.len()))
+                .await;
         }
-        Ok(resolver.aur_updates().await?)
+
+        Ok(updates)
     } else {
         Ok(AurUpdates::default())
     }
@@ -124,16 +182,16 @@ async fn get_aur_only_upgrades<'a, 'b>(
 
 async fn get_devel_upgrades(config: &Config, print: bool) -> Result<Vec<String>> {
     if config.devel && config.mode != "repo" {
-        let c = config.color;
-        if print {
-            println!(
-                "{} {}",
-                c.action.paint("::"),
-                c.bold.paint("Looking for devel upgrades")
-            );
+        let spinner = print.then(|| Spinner::new(config, "Looking for devel upgrades"));
+        let updates = possible_devel_updates(config).await?;
+
+        if let Some(spinner) = spinner {
+            spinner
+                .finish(config, &format!("{} devel upgrades", updates.len()))
+                .await;
         }
 
-        possible_devel_updates(config).await
+        Ok(updates)
     } else {
         Ok(Vec::new())
     }
@@ -154,7 +212,9 @@ pub async fn get_upgrades<'a, 'b>(
     config: &Config,
     resolver: &mut Resolver<'a, 'b>,
 ) -> Result<Upgrades> {
-    let (aur_upgrades, devel_upgrades) = aur_upgrades(config, resolver, true).await?;
+    let (aur_upgrades, devel_upgrades) =
+        aur_upgrades(config, resolver, !config.print_upgrades).await?;
+    let mut held_back = Vec::new();
 
     for pkg in aur_upgrades.ignored {
         eprintln!(
@@ -164,8 +224,18 @@ pub async fn get_upgrades<'a, 'b>(
             pkg.local.version(),
             pkg.remote.version
         );
+        held_back.push((
+            pkg.local.name().to_string(),
+            pkg.local.version().to_string(),
+            pkg.remote.version.clone(),
+        ));
     }
 
+    // Printed here, rather than deferred to print_summary below, since the ignored
+    // list is known before print_upgrades/empty-set/!upgrade_menu can return early.
+    // Routed to stderr in --print-upgrades mode so stdout stays parseable JSON.
+    print_held_back(config, &held_back);
+
     let mut aur_upgrades = aur_upgrades.updates;
     let mut devel_upgrades =
         filter_devel_updates(config, resolver.cache(), &devel_upgrades).await?;
@@ -180,6 +250,11 @@ pub async fn get_upgrades<'a, 'b>(
     devel_upgrades.dedup();
     aur_upgrades.retain(|u| !devel_upgrades.contains(&u.remote.name));
 
+    if config.print_upgrades {
+        print_upgrade_plan(config, &repo_upgrades, &aur_upgrades, &devel_upgrades)?;
+        return Ok(Upgrades::default());
+    }
+
     let mut repo_skip = Vec::new();
     let mut repo_keep = Vec::new();
     let mut aur_skip = Vec::new();
@@ -202,6 +277,11 @@ pub async fn get_upgrades<'a, 'b>(
             aur_skip,
             repo_skip,
         };
+        // Runs before this Upgrades is returned to the caller, so before any
+        // transaction built from it: it offers merging the .pacnew/.pacsave
+        // files already on disk for these packages, not ones that transaction
+        // will create.
+        pacdiff::run(config, &upgrades).await?;
         return Ok(upgrades);
     }
 
@@ -230,8 +310,12 @@ pub async fn get_upgrades<'a, 'b>(
         .max()
         .unwrap_or(0);
 
+    let mut kind_counts: HashMap<ChangeKind, usize> = HashMap::new();
+
     for (n, pkg) in repo_upgrades.iter().rev().enumerate().rev() {
         let local_pkg = config.alpm.localdb().pkg(pkg.name())?;
+        let kind = classify_change(local_pkg.version().as_str(), pkg.version());
+        *kind_counts.entry(kind).or_insert(0) += 1;
         print_upgrade(
             config,
             n + aur_upgrades.len() + devel_upgrades.len() + 1,
@@ -242,10 +326,13 @@ pub async fn get_upgrades<'a, 'b>(
             local_pkg.version(),
             old_max,
             pkg.version(),
+            kind,
         );
     }
 
     for (n, pkg) in aur_upgrades.iter().rev().enumerate().rev() {
+        let kind = classify_change(pkg.local.version(), &pkg.remote.version);
+        *kind_counts.entry(kind).or_insert(0) += 1;
         print_upgrade(
             config,
             n + devel_upgrades.len() + 1,
@@ -256,10 +343,12 @@ pub async fn get_upgrades<'a, 'b>(
             pkg.local.version(),
             old_max,
             &pkg.remote.version,
+            kind,
         );
     }
 
     for (n, pkg) in devel_upgrades.iter().rev().enumerate().rev() {
+        *kind_counts.entry(ChangeKind::Update).or_insert(0) += 1;
         print_upgrade(
             config,
             n + 1,
@@ -270,6 +359,7 @@ pub async fn get_upgrades<'a, 'b>(
             db.pkg(pkg.as_str()).unwrap().version(),
             old_max,
             "latest-commit",
+            ChangeKind::Update,
         );
     }
 
@@ -305,6 +395,30 @@ pub async fn get_upgrades<'a, 'b>(
         }
     }
 
+    let mut menu_held_back = Vec::new();
+
+    for pkg in &repo_skip {
+        if let Ok(new) = config.alpm.syncdbs().pkg(pkg.as_str()) {
+            let old = db.pkg(pkg.as_str())?.version().to_string();
+            menu_held_back.push((pkg.clone(), old, new.version().to_string()));
+        }
+    }
+
+    for pkg in &aur_skip {
+        if let Some(aur_pkg) = aur_upgrades.iter().find(|p| p.local.name() == pkg) {
+            menu_held_back.push((
+                pkg.clone(),
+                aur_pkg.local.version().to_string(),
+                aur_pkg.remote.version.clone(),
+            ));
+        } else if devel_upgrades.contains(pkg) {
+            let old = db.pkg(pkg.as_str())?.version().to_string();
+            menu_held_back.push((pkg.clone(), old, "latest-commit".to_string()));
+        }
+    }
+
+    print_summary(config, &kind_counts, &menu_held_back);
+
     let upgrades = Upgrades {
         repo_keep,
         repo_skip,
@@ -312,5 +426,220 @@ pub async fn get_upgrades<'a, 'b>(
         aur_skip,
     };
 
+    // As above: offer to resolve the .pacnew/.pacsave files already on disk for
+    // the kept packages before handing Upgrades back to the caller.
+    pacdiff::run(config, &upgrades).await?;
+
     Ok(upgrades)
 }
+
+// --print-upgrades must emit nothing but the JSON plan on stdout, so this is
+// routed to stderr whenever that mode is active.
+fn print_held_back(config: &Config, held_back: &[(String, String, String)]) {
+    if held_back.is_empty() {
+        return;
+    }
+
+    let c = config.color;
+    let header = format!(
+        "{} {}",
+        c.action.paint("::"),
+        c.warning.paint(format!("{} packages held back:", held_back.len()))
+    );
+
+    if config.print_upgrades {
+        eprintln!("{}", header);
+        for (pkg, old, new) in held_back {
+            let (old, new) = get_version_diff(config, old, new);
+            eprintln!("    {} {} -> {}", c.bold.paint(pkg), old, new);
+        }
+    } else {
+        println!("{}", header);
+        for (pkg, old, new) in held_back {
+            let (old, new) = get_version_diff(config, old, new);
+            println!("    {} {} -> {}", c.bold.paint(pkg), old, new);
+        }
+    }
+}
+
+fn print_summary(
+    config: &Config,
+    kind_counts: &HashMap<ChangeKind, usize>,
+    held_back: &[(String, String, String)],
+) {
+    let c = config.color;
+
+    let summary = [ChangeKind::Update, ChangeKind::Rebuild, ChangeKind::Epoch]
+        .into_iter()
+        .filter_map(|kind| kind_counts.get(&kind).map(|n| format!("{} {}", n, kind.label())))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if !summary.is_empty() {
+        println!("{} {}", c.action.paint("::"), c.bold.paint(summary));
+    }
+
+    print_held_back(config, held_back);
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct VersionDiff {
+    common: String,
+    old: String,
+    new: String,
+}
+
+impl VersionDiff {
+    fn new(old: &str, new: &str) -> VersionDiff {
+        let common = version_diff_common_len(old, new);
+
+        VersionDiff {
+            common: old[..common].to_string(),
+            old: old[common..].to_string(),
+            new: new[common..].to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct UpgradePlanEntry {
+    name: String,
+    source: String,
+    old_version: String,
+    new_version: String,
+    diff: VersionDiff,
+}
+
+#[derive(Serialize)]
+struct UpgradePlan {
+    upgrades: Vec<UpgradePlanEntry>,
+}
+
+// Serializes the same data gathered above (repo/aur/devel upgrades) as JSON
+// instead of rendering the interactive number menu, for `--print-upgrades`.
+fn print_upgrade_plan(
+    config: &Config,
+    repo_upgrades: &[alpm::Package],
+    aur_upgrades: &[aur_depends::Update],
+    devel_upgrades: &[String],
+) -> Result<()> {
+    let db = config.alpm.localdb();
+    let mut plan = UpgradePlan {
+        upgrades: Vec::new(),
+    };
+
+    if config.mode != "aur" {
+        for pkg in repo_upgrades {
+            let old = db.pkg(pkg.name())?.version().to_string();
+            let new = pkg.version().to_string();
+
+            plan.upgrades.push(UpgradePlanEntry {
+                name: pkg.name().to_string(),
+                source: pkg.db().unwrap().name().to_string(),
+                diff: VersionDiff::new(&old, &new),
+                old_version: old,
+                new_version: new,
+            });
+        }
+    }
+
+    if config.mode != "repo" {
+        for pkg in aur_upgrades {
+            let old = pkg.local.version().to_string();
+            let new = pkg.remote.version.clone();
+
+            plan.upgrades.push(UpgradePlanEntry {
+                name: pkg.local.name().to_string(),
+                source: "aur".to_string(),
+                diff: VersionDiff::new(&old, &new),
+                old_version: old,
+                new_version: new,
+            });
+        }
+
+        for pkg in devel_upgrades {
+            let old = db.pkg(pkg.as_str())?.version().to_string();
+            let new = "latest-commit".to_string();
+
+            plan.upgrades.push(UpgradePlanEntry {
+                name: pkg.clone(),
+                source: "devel".to_string(),
+                diff: VersionDiff::new(&old, &new),
+                old_version: old,
+                new_version: new,
+            });
+        }
+    }
+
+    println!("{}", serde_json::to_string(&plan)?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_epoch_extracts_epoch_when_present() {
+        assert_eq!(split_epoch("1:2.0-1"), ("1", "2.0-1"));
+    }
+
+    #[test]
+    fn split_epoch_defaults_to_zero_when_absent() {
+        assert_eq!(split_epoch("2.0-1"), ("0", "2.0-1"));
+    }
+
+    #[test]
+    fn classify_change_detects_epoch_bump() {
+        assert_eq!(classify_change("1.0-1", "1:1.0-1"), ChangeKind::Epoch);
+        assert_eq!(classify_change("1:1.0-1", "2:1.0-1"), ChangeKind::Epoch);
+    }
+
+    #[test]
+    fn classify_change_detects_rebuild_only() {
+        assert_eq!(classify_change("1.0-1", "1.0-2"), ChangeKind::Rebuild);
+        assert_eq!(classify_change("1:1.0-1", "1:1.0-2"), ChangeKind::Rebuild);
+    }
+
+    #[test]
+    fn classify_change_detects_plain_update() {
+        assert_eq!(classify_change("1.0-1", "1.1-1"), ChangeKind::Update);
+        assert_eq!(classify_change("1.0-1", "1.1-2"), ChangeKind::Update);
+    }
+
+    #[test]
+    fn version_diff_splits_on_common_prefix() {
+        assert_eq!(
+            VersionDiff::new("1.2.3-1", "1.2.4-1"),
+            VersionDiff {
+                common: "1.2.".to_string(),
+                old: "3-1".to_string(),
+                new: "4-1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn version_diff_handles_epoch_only_bump() {
+        assert_eq!(
+            VersionDiff::new("1.0-1", "1:1.0-1"),
+            VersionDiff {
+                common: String::new(),
+                old: "1.0-1".to_string(),
+                new: "1:1.0-1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn version_diff_serializes_as_plain_object() {
+        let diff = VersionDiff::new("1.0-1", "1.0-2");
+        let value = serde_json::to_value(&diff).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({ "common": "1.0-", "old": "1", "new": "2" })
+        );
+    }
+}